@@ -0,0 +1,286 @@
+//! Pluggable cache backends.
+//!
+//! [`Cache`] is the default, SQLite-backed implementation: a single `cache`
+//! table keyed by `(target, package, version)`, with schema upgrades
+//! applied through a small versioned migration list instead of discarding
+//! the whole cache whenever the on-disk format changes. [`redis::RedisCache`]
+//! implements the same [`CacheBackend`] trait for build fleets that want one
+//! shared, TTL'd cache across CI runners.
+
+mod redis;
+
+use super::{SearchResult, CACHE_EXPIRE};
+use crate::errors::*;
+use rusqlite::{params, Connection as SqliteConnection, OptionalExtension};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub use redis::RedisCache;
+
+/// A place to store the result of a `target`/`package`/`version` lookup,
+/// shared by the filesystem-local SQLite cache and the Redis-backed one.
+pub trait CacheBackend {
+    fn get(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<SearchResult>, Error>;
+
+    fn put(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+        found: SearchResult,
+    ) -> Result<(), Error>;
+
+    /// Write many entries at once, e.g. after a batched resolve. The default
+    /// just calls [`CacheBackend::put`] per entry; backends that can commit
+    /// them together (like the SQLite-backed [`Cache`]) should override this.
+    fn put_many(
+        &self,
+        target: &str,
+        entries: &[(String, String, SearchResult)],
+    ) -> Result<(), Error> {
+        for (package, version, found) in entries {
+            self.put(target, package, version, *found)?;
+        }
+        Ok(())
+    }
+
+    /// Drop expired entries for the `--prune` subcommand. Backends that rely
+    /// on native key TTLs (like Redis) expire entries on their own and can
+    /// leave this as a no-op.
+    fn prune(&self) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    /// Row count for the `--cache-stats` subcommand. Backends without a
+    /// cheap way to count entries can leave this as a no-op.
+    fn stats(&self) -> Result<usize, Error> {
+        Ok(0)
+    }
+}
+
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS cache (
+        target     TEXT NOT NULL,
+        package    TEXT NOT NULL,
+        version    TEXT NOT NULL,
+        result     INTEGER NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        PRIMARY KEY (target, package, version)
+    );",
+];
+
+pub struct Cache {
+    conn: SqliteConnection,
+}
+
+impl Cache {
+    pub fn open(path: &Path) -> Result<Cache, Error> {
+        let conn = SqliteConnection::open(path)?;
+        let cache = Cache { conn };
+        cache.migrate()?;
+        Ok(cache)
+    }
+
+    fn migrate(&self) -> Result<(), Error> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);",
+        )?;
+
+        let current: i64 = self
+            .conn
+            .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+            .optional()?
+            .unwrap_or(0);
+
+        if current as usize >= MIGRATIONS.len() {
+            return Ok(());
+        }
+
+        // Run every pending migration plus the schema_version bump as one
+        // transaction, so a crash mid-migration can't leave the version
+        // behind what's actually on disk and re-run a `CREATE TABLE` that
+        // already succeeded.
+        self.conn.execute_batch("BEGIN")?;
+
+        let result = (|| -> Result<(), Error> {
+            for (i, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+                debug!("Applying cache migration {}", i + 1);
+                self.conn.execute_batch(migration)?;
+            }
+
+            self.conn.execute("DELETE FROM schema_version", [])?;
+            self.conn.execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![MIGRATIONS.len() as i64],
+            )?;
+
+            Ok(())
+        })();
+
+        if result.is_ok() {
+            self.conn.execute_batch("COMMIT")?;
+        } else {
+            let _ = self.conn.execute_batch("ROLLBACK");
+        }
+
+        result
+    }
+
+    pub fn get(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<SearchResult>, Error> {
+        let row: Option<(i64, i64)> = self
+            .conn
+            .query_row(
+                "SELECT result, fetched_at FROM cache WHERE target = ?1 AND package = ?2 AND version = ?3",
+                params![target, package, version],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let (result, fetched_at) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let fetched_at = UNIX_EPOCH + std::time::Duration::from_secs(fetched_at as u64);
+        if SystemTime::now().duration_since(fetched_at)? > CACHE_EXPIRE {
+            return Ok(None);
+        }
+
+        Ok(Some(decode_result(result)))
+    }
+
+    pub fn put(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+        found: SearchResult,
+    ) -> Result<(), Error> {
+        let fetched_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        self.conn.execute(
+            "INSERT INTO cache (target, package, version, result, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (target, package, version)
+             DO UPDATE SET result = excluded.result, fetched_at = excluded.fetched_at",
+            params![target, package, version, encode_result(found), fetched_at],
+        )?;
+        Ok(())
+    }
+
+    /// Write many entries in a single transaction, so a batched resolve
+    /// costs one fsync instead of one per entry.
+    pub fn put_many(
+        &self,
+        target: &str,
+        entries: &[(String, String, SearchResult)],
+    ) -> Result<(), Error> {
+        self.conn.execute_batch("BEGIN")?;
+
+        let result = (|| -> Result<(), Error> {
+            for (package, version, found) in entries {
+                let fetched_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+                self.conn.execute(
+                    "INSERT INTO cache (target, package, version, result, fetched_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (target, package, version)
+                     DO UPDATE SET result = excluded.result, fetched_at = excluded.fetched_at",
+                    params![target, package, version, encode_result(*found), fetched_at],
+                )?;
+            }
+            Ok(())
+        })();
+
+        if result.is_ok() {
+            self.conn.execute_batch("COMMIT")?;
+        } else {
+            let _ = self.conn.execute_batch("ROLLBACK");
+        }
+
+        result
+    }
+
+    /// Drop every expired entry, returning how many rows were removed.
+    pub fn prune(&self) -> Result<usize, Error> {
+        let cutoff = SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs()
+            .saturating_sub(CACHE_EXPIRE.as_secs()) as i64;
+        let removed = self
+            .conn
+            .execute("DELETE FROM cache WHERE fetched_at < ?1", params![cutoff])?;
+        Ok(removed)
+    }
+
+    /// Row count, used to back the `--cache-stats` subcommand.
+    pub fn stats(&self) -> Result<usize, Error> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT count(*) FROM cache", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+}
+
+impl CacheBackend for Cache {
+    fn get(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<SearchResult>, Error> {
+        Cache::get(self, target, package, version)
+    }
+
+    fn put(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+        found: SearchResult,
+    ) -> Result<(), Error> {
+        Cache::put(self, target, package, version, found)
+    }
+
+    fn put_many(
+        &self,
+        target: &str,
+        entries: &[(String, String, SearchResult)],
+    ) -> Result<(), Error> {
+        Cache::put_many(self, target, entries)
+    }
+
+    fn prune(&self) -> Result<usize, Error> {
+        Cache::prune(self)
+    }
+
+    fn stats(&self) -> Result<usize, Error> {
+        Cache::stats(self)
+    }
+}
+
+fn encode_result(found: SearchResult) -> i64 {
+    match found {
+        SearchResult::Found => 0,
+        SearchResult::FoundOutdated => 1,
+        SearchResult::NotFound => 2,
+        SearchResult::FoundIncompatible => 3,
+    }
+}
+
+fn decode_result(value: i64) -> SearchResult {
+    match value {
+        0 => SearchResult::Found,
+        1 => SearchResult::FoundOutdated,
+        3 => SearchResult::FoundIncompatible,
+        _ => SearchResult::NotFound,
+    }
+}
@@ -0,0 +1,54 @@
+//! Redis-backed [`CacheBackend`] so a whole CI fleet can share one cache
+//! instead of every runner re-querying udd-mirror on a cold filesystem.
+//!
+//! Entries are keyed by the same `target`/`package`/`version` triple as the
+//! SQLite cache, joined into one `target-package-version` string, and rely on Redis's own key TTL for the 90 minute expiry instead of
+//! storing a `SystemTime` alongside the value.
+
+use super::{decode_result, encode_result, CacheBackend, SearchResult, CACHE_EXPIRE};
+use crate::errors::*;
+use redis::Commands;
+
+pub struct RedisCache {
+    client: redis::Client,
+}
+
+impl RedisCache {
+    pub fn open(url: &str) -> Result<RedisCache, Error> {
+        let client = redis::Client::open(url)?;
+        Ok(RedisCache { client })
+    }
+
+    fn key(target: &str, package: &str, version: &str) -> String {
+        format!("{}-{}-{}", target, package, version)
+    }
+}
+
+impl CacheBackend for RedisCache {
+    fn get(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<SearchResult>, Error> {
+        let mut conn = self.client.get_connection()?;
+        let value: Option<i64> = conn.get(Self::key(target, package, version))?;
+        Ok(value.map(decode_result))
+    }
+
+    fn put(
+        &self,
+        target: &str,
+        package: &str,
+        version: &str,
+        found: SearchResult,
+    ) -> Result<(), Error> {
+        let mut conn = self.client.get_connection()?;
+        conn.set_ex(
+            Self::key(target, package, version),
+            encode_result(found),
+            CACHE_EXPIRE.as_secs(),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! dpkg-style version comparison.
+//!
+//! Debian source versions are `[epoch:]upstream[-revision]`, where `upstream`
+//! and `revision` are compared with dpkg's own ordering rules rather than
+//! semver: each is walked in alternating non-digit/digit segments, digit
+//! segments compare as integers, and non-digit segments compare
+//! character-by-character where `~` sorts before everything (including
+//! end-of-string), end-of-string sorts before any real character, and
+//! letters sort before non-letter characters.
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+pub struct DebianVersion<'a> {
+    pub epoch: u64,
+    pub upstream: &'a str,
+    pub revision: &'a str,
+}
+
+impl<'a> DebianVersion<'a> {
+    pub fn parse(version: &'a str) -> DebianVersion<'a> {
+        let (epoch, rest) = match version.find(':') {
+            Some(idx) => (version[..idx].parse().unwrap_or(0), &version[idx + 1..]),
+            None => (0, version),
+        };
+
+        let (upstream, revision) = match rest.rfind('-') {
+            Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+            None => (rest, ""),
+        };
+
+        DebianVersion {
+            epoch,
+            upstream,
+            revision,
+        }
+    }
+}
+
+/// Best-effort `(major, minor, patch)` extracted from an upstream version
+/// string that isn't necessarily valid semver (e.g. `1.0.0~beta.1`). Each
+/// component is the leading digit run of its dot-separated segment; a
+/// missing or non-numeric segment defaults to 0.
+pub fn leading_triplet(upstream: &str) -> (u64, u64, u64) {
+    let mut parts = upstream.splitn(3, '.');
+    let major = parts.next().map(leading_digits).unwrap_or(0);
+    let minor = parts.next().map(leading_digits).unwrap_or(0);
+    let patch = parts.next().map(leading_digits).unwrap_or(0);
+    (major, minor, patch)
+}
+
+fn leading_digits(s: &str) -> u64 {
+    let digits: String = s.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().unwrap_or(0)
+}
+
+/// Compare two full `[epoch:]upstream[-revision]` version strings the way
+/// `dpkg --compare-versions` would.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let a = DebianVersion::parse(a);
+    let b = DebianVersion::parse(b);
+
+    a.epoch
+        .cmp(&b.epoch)
+        .then_with(|| compare_part(a.upstream, b.upstream))
+        .then_with(|| compare_part(a.revision, b.revision))
+}
+
+fn compare_part(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        let cmp = compare_non_digits(&mut a, &mut b);
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+
+        let an = take_digits(&mut a);
+        let bn = take_digits(&mut b);
+        let cmp = an.cmp(&bn);
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+
+        if a.peek().is_none() && b.peek().is_none() {
+            return Ordering::Equal;
+        }
+    }
+}
+
+fn compare_non_digits(a: &mut Peekable<Chars>, b: &mut Peekable<Chars>) -> Ordering {
+    loop {
+        let ca = a.peek().copied().filter(|c| !c.is_ascii_digit());
+        let cb = b.peek().copied().filter(|c| !c.is_ascii_digit());
+
+        if ca.is_none() && cb.is_none() {
+            return Ordering::Equal;
+        }
+
+        let cmp = rank(ca).cmp(&rank(cb));
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+
+        if ca.is_some() {
+            a.next();
+        }
+        if cb.is_some() {
+            b.next();
+        }
+    }
+}
+
+fn take_digits(it: &mut Peekable<Chars>) -> u64 {
+    let mut digits = String::new();
+    while let Some(&c) = it.peek() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            it.next();
+        } else {
+            break;
+        }
+    }
+    digits.parse().unwrap_or(0)
+}
+
+/// Sort key for a single non-digit character (or the lack of one): `~` first,
+/// then end-of-string, then letters, then everything else, each ordered by
+/// their own value within the tier.
+fn rank(c: Option<char>) -> (u8, u32) {
+    match c {
+        Some('~') => (0, 0),
+        None => (1, 0),
+        Some(c) if c.is_ascii_alphabetic() => (2, c as u32),
+        Some(c) => (3, c as u32),
+    }
+}